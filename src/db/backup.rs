@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::{compress, DirList};
+
+/// Number of rotated backups kept alongside `db.zo` by default.
+pub(super) const DEFAULT_DEPTH: usize = 5;
+
+/// Reads `db.zo` in `data_dir`, recovering from the backup ring if the
+/// snapshot is missing or fails to deserialize. Returns the on-disk size,
+/// generation number, and decoded bytes of whichever file was used, ready
+/// for `DirList::from_bytes`, or `None` if there's no database and no
+/// usable backup.
+pub(super) fn read(data_dir: &Path, depth: usize) -> Result<Option<(u64, u64, Vec<u8>)>> {
+    let path = super::db_path(data_dir);
+    match fs::read(&path) {
+        Ok(buffer) => {
+            if let Ok((generation, decoded)) = decode_and_validate(&buffer) {
+                return Ok(Some((buffer.len() as u64, generation, decoded)));
+            }
+
+            let corrupt_path = data_dir.join("db.zo.corrupt");
+            let _ = writeln!(
+                io::stderr(),
+                "zoxide: database is corrupt, moving it aside and trying backups: {}",
+                path.display()
+            );
+            fs::rename(&path, &corrupt_path).with_context(|| {
+                format!("could not move aside corrupt database: {}", path.display())
+            })?;
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("could not read from database: {}", path.display()));
+        }
+    }
+
+    for idx in 0..depth {
+        let backup = backup_path(data_dir, idx);
+        let Ok(buffer) = fs::read(&backup) else { continue };
+        if let Ok((generation, decoded)) = decode_and_validate(&buffer) {
+            let _ = writeln!(io::stderr(), "zoxide: recovered database from backup: {}", backup.display());
+            return Ok(Some((buffer.len() as u64, generation, decoded)));
+        }
+    }
+
+    Ok(None)
+}
+
+fn decode_and_validate(buffer: &[u8]) -> Result<(u64, Vec<u8>)> {
+    let (generation, decoded) = compress::decode(buffer.to_vec())?;
+    DirList::from_bytes(&decoded)?;
+    Ok((generation, decoded))
+}
+
+/// Rotates `db.zo.bak.0..depth` one slot older, dropping the oldest, and
+/// moves the current `db.zo` into the freed `db.zo.bak.0` slot. Must be
+/// called before a fresh snapshot is written in its place, so a crash
+/// mid-rotation still leaves a readable database: either the prior
+/// snapshot, still under a name [`read`] knows to look for, or already
+/// rotated into the ring.
+pub(super) fn rotate(data_dir: &Path, depth: usize) -> Result<()> {
+    if depth == 0 {
+        return Ok(());
+    }
+
+    let oldest = backup_path(data_dir, depth - 1);
+    if oldest.exists() {
+        fs::remove_file(&oldest)
+            .with_context(|| format!("could not remove old backup: {}", oldest.display()))?;
+    }
+
+    for idx in (0..depth - 1).rev() {
+        let from = backup_path(data_dir, idx);
+        if !from.exists() {
+            continue;
+        }
+        fs::rename(&from, backup_path(data_dir, idx + 1))
+            .with_context(|| format!("could not rotate backup: {}", from.display()))?;
+    }
+
+    let current = super::db_path(data_dir);
+    if current.exists() {
+        fs::rename(&current, backup_path(data_dir, 0))
+            .with_context(|| format!("could not back up database: {}", current.display()))?;
+    }
+
+    Ok(())
+}
+
+fn backup_path(data_dir: &Path, idx: usize) -> PathBuf {
+    data_dir.join(format!("db.zo.bak.{idx}"))
+}
@@ -0,0 +1,53 @@
+use anyhow::{bail, Context, Result};
+
+const MAGIC: &[u8; 4] = b"ZOXI";
+const VERSION_COMPRESSED: u8 = 1;
+
+/// Default zstd level for compacted snapshots: fast, trading ratio for
+/// speed, since compaction is the rare case and `add`/`remove` never pay
+/// this cost.
+pub(super) const DEFAULT_LEVEL: i32 = 3;
+
+/// Compresses `raw` and prepends a short magic + version header plus the
+/// snapshot's generation number, so [`decode`] can tell a compressed
+/// snapshot apart from a legacy, uncompressed one, and [`super::log`] can
+/// tell whether its own records are already folded into a given snapshot.
+pub(super) fn encode(raw: &[u8], level: i32, generation: u64) -> Result<Vec<u8>> {
+    let compressed = zstd::stream::encode_all(raw, level).context("could not compress database")?;
+
+    let mut buffer = Vec::with_capacity(MAGIC.len() + 1 + 8 + compressed.len());
+    buffer.extend_from_slice(MAGIC);
+    buffer.push(VERSION_COMPRESSED);
+    buffer.extend_from_slice(&generation.to_le_bytes());
+    buffer.extend_from_slice(&compressed);
+    Ok(buffer)
+}
+
+/// Decompresses `buffer` if it carries our magic + version header, also
+/// returning its generation number. A file without the magic is assumed
+/// to be a legacy, uncompressed snapshot and is returned unchanged with
+/// generation `0`, so upgrades are seamless and downgrades degrade
+/// gracefully. A file with the magic but a
+/// truncated header or an unknown version is an error.
+pub(super) fn decode(buffer: Vec<u8>) -> Result<(u64, Vec<u8>)> {
+    let Some(rest) = buffer.strip_prefix(MAGIC.as_slice()) else {
+        return Ok((0, buffer));
+    };
+    let Some((&version, rest)) = rest.split_first() else {
+        bail!("truncated database header");
+    };
+
+    match version {
+        VERSION_COMPRESSED => {
+            if rest.len() < 8 {
+                bail!("truncated database header");
+            }
+            let (generation, compressed) = rest.split_at(8);
+            let generation = u64::from_le_bytes(generation.try_into().unwrap());
+            let decoded =
+                zstd::stream::decode_all(compressed).context("could not decompress database")?;
+            Ok((generation, decoded))
+        }
+        version => bail!("unsupported database version: {version}"),
+    }
+}
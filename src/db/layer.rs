@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// Whether a [`Layer`] may be written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerState {
+    /// The layer may be mutated. Exactly one layer in a [`DatabaseFile`]
+    /// must be `Active { writable: true }`.
+    ///
+    /// [`DatabaseFile`]: super::DatabaseFile
+    Active { writable: bool },
+    /// The layer is only ever read, e.g. a curated database shipped
+    /// system-wide. It's consulted for ranking but never written to.
+    ReadOnly,
+}
+
+impl LayerState {
+    pub(super) fn is_writable(self) -> bool {
+        matches!(self, LayerState::Active { writable: true })
+    }
+}
+
+/// A single data directory backing a [`DatabaseFile`], and whether it may
+/// be written to.
+///
+/// [`DatabaseFile`]: super::DatabaseFile
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub data_dir: PathBuf,
+    pub state: LayerState,
+}
+
+impl Layer {
+    /// A layer that can be read from and written to.
+    pub fn writable<P: Into<PathBuf>>(data_dir: P) -> Self {
+        Layer { data_dir: data_dir.into(), state: LayerState::Active { writable: true } }
+    }
+
+    /// A layer that is only ever merged in for ranking, never written to.
+    pub fn read_only<P: Into<PathBuf>>(data_dir: P) -> Self {
+        Layer { data_dir: data_dir.into(), state: LayerState::ReadOnly }
+    }
+}
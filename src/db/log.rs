@@ -0,0 +1,236 @@
+use anyhow::{bail, Context, Result};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::{Dir, DirList, Epoch, Rank};
+
+const OP_ADD: u8 = 1;
+const OP_REMOVE: u8 = 2;
+
+/// Size in bytes of the generation header at the start of every change
+/// log, ahead of its records.
+const HEADER_LEN: usize = 8;
+
+/// An append-only log of mutations made to a [`DirList`] since the last
+/// snapshot was written to `db.zo`. Each record is appended with a single
+/// `write_all` + `sync_data`, so `add`/`remove` never need to rewrite the
+/// full database.
+///
+/// The log is tagged with the generation number of the snapshot it's
+/// relative to (see [`HEADER_LEN`]). A snapshot is only ever made current
+/// after it's durably persisted, but the log that fed it is truncated
+/// afterwards in a separate write -- if a crash happens in between, the
+/// next `open` would see a snapshot that already includes the log's
+/// records, plus the untruncated log still sitting there. Comparing
+/// generations catches this: a log whose stamped generation doesn't match
+/// the snapshot that was just loaded is known to be stale, so its records
+/// are discarded instead of being replayed (and double-counted) again.
+pub(super) struct ChangeLog {
+    file: File,
+    path: PathBuf,
+    len: u64,
+    records: u64,
+    generation: u64,
+}
+
+impl ChangeLog {
+    /// Opens (creating if necessary) the change log sidecar for `data_dir`,
+    /// replaying its records onto `dirs` if it's stamped with
+    /// `snapshot_generation`, the generation of the snapshot `dirs` was
+    /// just loaded from. A log stamped with any other generation is
+    /// stale -- its records are already folded into `dirs`, or belong to a
+    /// snapshot that's gone -- so it's discarded and restarted instead.
+    pub(super) fn open(
+        data_dir: &Path,
+        dirs: &mut DirList,
+        snapshot_generation: u64,
+    ) -> Result<Self> {
+        let path = log_path(data_dir);
+
+        let buffer = match fs::read(&path) {
+            Ok(buffer) => buffer,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("could not read from change log: {}", path.display()))
+            }
+        };
+
+        let file = OpenOptions::new().create(true).write(true).read(true).open(&path).with_context(|| {
+            format!("could not open change log: {}", path.display())
+        })?;
+
+        let mut log =
+            ChangeLog { file, path, len: 0, records: 0, generation: snapshot_generation };
+
+        let stored_generation = (buffer.len() >= HEADER_LEN)
+            .then(|| u64::from_le_bytes(buffer[..HEADER_LEN].try_into().unwrap()));
+
+        match stored_generation {
+            Some(generation) if generation == snapshot_generation => {
+                let records_buffer = &buffer[HEADER_LEN..];
+                log.records = replay(records_buffer, dirs).with_context(|| {
+                    format!("could not replay change log: {}", log.path.display())
+                })?;
+                log.len = records_buffer.len() as u64;
+            }
+            _ => log.reset()?,
+        }
+
+        Ok(log)
+    }
+
+    /// Appends an `add` record and fsyncs it before returning.
+    pub(super) fn append_add(
+        &mut self,
+        path: &str,
+        last_accessed: Epoch,
+        rank: Rank,
+    ) -> Result<()> {
+        let mut record = Vec::with_capacity(path.len() + 17);
+        record.push(OP_ADD);
+        encode_path(&mut record, path);
+        record.extend_from_slice(&last_accessed.to_le_bytes());
+        record.extend_from_slice(&rank.to_le_bytes());
+        self.append(&record)
+    }
+
+    /// Appends a `remove` record and fsyncs it before returning.
+    pub(super) fn append_remove(&mut self, path: &str) -> Result<()> {
+        let mut record = Vec::with_capacity(path.len() + 5);
+        record.push(OP_REMOVE);
+        encode_path(&mut record, path);
+        self.append(&record)
+    }
+
+    fn append(&mut self, record: &[u8]) -> Result<()> {
+        self.file
+            .seek(SeekFrom::End(0))
+            .and_then(|_| self.file.write_all(record))
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| format!("could not append to change log: {}", self.path.display()))?;
+
+        self.len += record.len() as u64;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Size in bytes of the records appended since the last snapshot.
+    pub(super) fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Number of records appended since the last snapshot.
+    pub(super) fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Truncates the log and re-stamps it with `new_generation`. Must only
+    /// be called once the snapshot for `new_generation` has been durably
+    /// persisted. If a crash happens between that persist and this call,
+    /// the log on disk keeps its old generation stamp, so the next `open`
+    /// (seeing the new snapshot's generation) recognizes it as stale and
+    /// discards it instead of replaying already-applied records.
+    pub(super) fn clear(&mut self, new_generation: u64) -> Result<()> {
+        self.generation = new_generation;
+        self.reset()
+    }
+
+    /// Truncates the log and writes the current generation header, without
+    /// changing `self.generation`. Used both by `clear` and to discard a
+    /// stale log found at `open`.
+    fn reset(&mut self) -> Result<()> {
+        self.file
+            .set_len(0)
+            .with_context(|| format!("could not truncate change log: {}", self.path.display()))?;
+        self.file
+            .seek(SeekFrom::Start(0))
+            .and_then(|_| self.file.write_all(&self.generation.to_le_bytes()))
+            .and_then(|_| self.file.sync_data())
+            .with_context(|| {
+                format!("could not stamp change log: {}", self.path.display())
+            })?;
+        self.len = 0;
+        self.records = 0;
+        Ok(())
+    }
+}
+
+fn encode_path(buf: &mut Vec<u8>, path: &str) {
+    let bytes = path.as_bytes();
+    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+/// Replays every record in `buffer` onto `dirs`, applying each one through
+/// the same logic as `Database::add` / `Database::remove`. Returns the
+/// number of records replayed.
+fn replay(mut buffer: &[u8], dirs: &mut DirList) -> Result<u64> {
+    let mut records = 0;
+
+    while !buffer.is_empty() {
+        let opcode = take_u8(&mut buffer)?;
+        let path = take_path(&mut buffer)?;
+
+        match opcode {
+            OP_ADD => {
+                let last_accessed = take_epoch(&mut buffer)?;
+                let rank = take_rank(&mut buffer)?;
+
+                match dirs.iter_mut().find(|dir| dir.path == path) {
+                    None => dirs.push(Dir { path: path.into(), last_accessed, rank }),
+                    Some(dir) => {
+                        dir.last_accessed = last_accessed;
+                        dir.rank += rank;
+                    }
+                }
+            }
+            OP_REMOVE => {
+                if let Some(idx) = dirs.iter().position(|dir| dir.path == path) {
+                    dirs.swap_remove(idx);
+                }
+            }
+            opcode => bail!("unknown opcode: {opcode}"),
+        }
+
+        records += 1;
+    }
+
+    Ok(records)
+}
+
+fn take_u8(buffer: &mut &[u8]) -> Result<u8> {
+    let (&byte, rest) = buffer.split_first().context("unexpected end of file")?;
+    *buffer = rest;
+    Ok(byte)
+}
+
+fn take_n<'a>(buffer: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    if buffer.len() < n {
+        bail!("unexpected end of file");
+    }
+    let (taken, rest) = buffer.split_at(n);
+    *buffer = rest;
+    Ok(taken)
+}
+
+fn take_path(buffer: &mut &[u8]) -> Result<String> {
+    let len = u32::from_le_bytes(take_n(buffer, 4)?.try_into().unwrap()) as usize;
+    let bytes = take_n(buffer, len)?;
+    String::from_utf8(bytes.to_vec()).context("invalid UTF-8 in path")
+}
+
+fn take_epoch(buffer: &mut &[u8]) -> Result<Epoch> {
+    Ok(Epoch::from_le_bytes(take_n(buffer, 8)?.try_into().unwrap()))
+}
+
+fn take_rank(buffer: &mut &[u8]) -> Result<Rank> {
+    Ok(Rank::from_le_bytes(take_n(buffer, 8)?.try_into().unwrap()))
+}
+
+fn log_path(data_dir: &Path) -> PathBuf {
+    const LOG_FILENAME: &str = "db.zo.log";
+    data_dir.join(LOG_FILENAME)
+}
@@ -1,7 +1,12 @@
+mod backup;
+mod compress;
 mod dir;
+mod layer;
+mod log;
 mod stream;
 
 pub use dir::{Dir, DirList, Epoch, Rank};
+pub use layer::{Layer, LayerState};
 pub use stream::Stream;
 
 use anyhow::{Context, Result};
@@ -11,20 +16,120 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
+/// Once the change log grows past this many records, it's folded into a
+/// fresh snapshot even if it's still smaller than the snapshot itself.
+const COMPACT_MAX_RECORDS: u64 = 4096;
+
 #[derive(Debug)]
 pub struct Database<'file> {
     pub dirs: DirList<'file>,
     pub modified: bool,
     pub data_dir: &'file PathBuf,
+    log: log::ChangeLog,
+    snapshot_len: u64,
+    /// Generation number of the currently-persisted snapshot, bumped on
+    /// every `compact`. Lets the change log tell whether its own records
+    /// are already folded into the snapshot it was opened alongside.
+    snapshot_generation: u64,
+    force_compact: bool,
+    /// Read-only layers, consulted for ranking but never mutated or persisted.
+    read_only: Vec<DirList<'file>>,
+    /// Whether `read_only` has already been folded into `dirs` for `stream`.
+    layers_merged: bool,
+    /// Paths pushed into `dirs` by `merge_read_only_layers` that didn't
+    /// already exist in the writable layer. Removed again by
+    /// `unmerge_read_only_layers` before any further mutation or
+    /// persistence, so they never leak into the writable snapshot.
+    merged_new: Vec<String>,
+    /// For paths `merge_read_only_layers` found already present in the
+    /// writable layer, their rank/last_accessed from before the read-only
+    /// layer's contribution was folded in, so it can be restored by
+    /// `unmerge_read_only_layers`.
+    merged_overlay: Vec<(String, Epoch, Rank)>,
+    /// zstd level used when compacting the writable layer's snapshot.
+    compression_level: i32,
+    /// Number of rotated `db.zo.bak.*` backups to keep.
+    backup_depth: usize,
+    /// Maximum number of directories to keep, evicting the lowest-scoring
+    /// ones once exceeded.
+    max_entries: Option<usize>,
+    /// Maximum serialized (compressed, on-disk) size of the snapshot, in
+    /// bytes, evicting the lowest-scoring entries once exceeded.
+    max_bytes: Option<u64>,
+    /// The most recent `now` passed to `add` (`age` takes no `now` of its
+    /// own), used to score entries for eviction when enforcing
+    /// `max_entries`/`max_bytes`. Defaults to `0` until `add` is called at
+    /// least once; `evict_lowest_scoring` falls back to the highest
+    /// `last_accessed` it can find in that case.
+    last_now: Epoch,
 }
 
 impl<'file> Database<'file> {
     pub fn save(&mut self) -> Result<()> {
+        self.unmerge_read_only_layers();
+
         if !self.modified {
             return Ok(());
         }
 
-        let buffer = self.dirs.to_bytes()?;
+        if self.should_compact() {
+            self.compact()?;
+        }
+
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Whether the change log has grown enough to be worth folding into a
+    /// fresh snapshot, rather than left to grow unbounded, or whether
+    /// `max_entries`/`max_bytes` are currently exceeded and need enforcing.
+    fn should_compact(&self) -> bool {
+        if self.force_compact
+            || self.log.len() > self.snapshot_len
+            || self.log.records() > COMPACT_MAX_RECORDS
+            || self.max_entries.is_some_and(|max| self.dirs.len() > max)
+        {
+            return true;
+        }
+
+        let Some(max_bytes) = self.max_bytes else { return false };
+
+        // `snapshot_len + log.len()` is a cheap upper bound on the size a
+        // fresh compaction would produce: the log only ever adds bytes on
+        // top of the last compacted snapshot. Only pay for an actual
+        // serialize + zstd pass once that bound is in question, so a
+        // normal `add`/`remove` save stays O(1) instead of re-compressing
+        // the whole database on every write.
+        if self.snapshot_len + self.log.len() <= max_bytes {
+            return false;
+        }
+
+        let Ok(raw) = self.dirs.to_bytes() else { return false };
+        let Ok(buffer) = compress::encode(&raw, self.compression_level, self.snapshot_generation)
+        else {
+            return false;
+        };
+        buffer.len() as u64 > max_bytes
+    }
+
+    /// Writes a fresh snapshot of `self.dirs`, tagged with a bumped
+    /// generation number, and truncates the change log to match.
+    ///
+    /// The snapshot is persisted before the log is truncated, so a crash in
+    /// between can leave the new snapshot on disk alongside the old,
+    /// untruncated log. That log is still stamped with the *old*
+    /// generation, though, so the next `open` can tell its records are
+    /// already folded into the new snapshot and discard them instead of
+    /// replaying (and double-counting) them.
+    fn compact(&mut self) -> Result<()> {
+        self.enforce_caps()?;
+
+        let raw = self.dirs.to_bytes()?;
+        let generation = self.snapshot_generation.wrapping_add(1);
+        let buffer = compress::encode(&raw, self.compression_level, generation)?;
+
+        backup::rotate(&self.data_dir, self.backup_depth)?;
+
         let mut file = NamedTempFile::new_in(&self.data_dir).with_context(|| {
             format!("could not create temporary database in: {}", self.data_dir.display())
         })?;
@@ -41,13 +146,101 @@ impl<'file> Database<'file> {
         persist(file, &path)
             .with_context(|| format!("could not replace database: {}", path.display()))?;
 
-        self.modified = false;
+        self.log.clear(generation)?;
+        self.snapshot_len = buffer.len() as u64;
+        self.snapshot_generation = generation;
+        self.force_compact = false;
+        Ok(())
+    }
+
+    /// Evicts the lowest-scoring entries until `self.dirs` fits within
+    /// `max_entries` and `max_bytes`, using the same frecency score
+    /// [`Stream`] ranks by. This is independent of `age`: it can drop
+    /// entries that an `age` pass would keep.
+    fn enforce_caps(&mut self) -> Result<()> {
+        if self.max_entries.is_none() && self.max_bytes.is_none() {
+            return Ok(());
+        }
+
+        if let Some(max_entries) = self.max_entries {
+            if self.dirs.len() > max_entries {
+                self.evict_lowest_scoring(self.dirs.len() - max_entries);
+            }
+        }
+
+        // Unlike `max_entries`, `max_bytes` can only be checked by actually
+        // compressing `dirs`, which is too expensive to redo after every
+        // single eviction. Instead, estimate how many entries to drop from
+        // the overage using the average compressed bytes per entry, and
+        // only re-check (and re-estimate) if that undershoots.
+        if let Some(max_bytes) = self.max_bytes {
+            while !self.dirs.is_empty() {
+                let raw = self.dirs.to_bytes()?;
+                let len = compress::encode(&raw, self.compression_level, self.snapshot_generation)?
+                    .len() as u64;
+                if len <= max_bytes {
+                    break;
+                }
+
+                let overage = len - max_bytes;
+                let per_entry = (len / self.dirs.len() as u64).max(1);
+                let to_evict =
+                    ((overage / per_entry) + 1).min(self.dirs.len() as u64) as usize;
+                self.evict_lowest_scoring(to_evict);
+            }
+        }
+
         Ok(())
     }
 
+    /// Removes the `count` lowest-scoring entries in one pass, using the
+    /// same frecency score [`Stream`] ranks by.
+    fn evict_lowest_scoring(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        // `last_now` is only ever set by `add`. If eviction is triggered by
+        // a session that only called `remove`/`age`/`dedup`, it's still its
+        // initial `0`, which would make every entry look impossibly old and
+        // collapse scoring to raw rank. Fall back to the most recent
+        // `last_accessed` we actually know about instead.
+        let now = if self.last_now > 0 {
+            self.last_now
+        } else {
+            self.dirs.iter().map(|dir| dir.last_accessed).max().unwrap_or(0)
+        };
+        let mut indices: Vec<usize> = (0..self.dirs.len()).collect();
+        indices.sort_by(|&a, &b| self.dirs[a].score(now).total_cmp(&self.dirs[b].score(now)));
+
+        // Remove in descending index order, so each `swap_remove` can't
+        // invalidate an index still pending removal.
+        let mut to_remove: Vec<usize> = indices.into_iter().take(count).collect();
+        to_remove.sort_unstable_by(|a, b| b.cmp(a));
+        for idx in to_remove {
+            self.dirs.swap_remove(idx);
+        }
+
+        self.force_compact = true;
+    }
+
+    /// Appends a mutation to the change log. A failure here is non-fatal --
+    /// we print a warning and fall back to rewriting the full snapshot on
+    /// the next `save`, same as before the log existed.
+    fn log_mutation(&mut self, write: impl FnOnce(&mut log::ChangeLog) -> Result<()>) {
+        if let Err(e) = write(&mut self.log) {
+            let _ = writeln!(io::stderr(), "zoxide: {:?}", e);
+            self.force_compact = true;
+        }
+        self.modified = true;
+    }
+
     /// Adds a new directory or increments its rank. Also updates its last accessed time.
     pub fn add<S: AsRef<str>>(&mut self, path: S, now: Epoch) {
+        self.unmerge_read_only_layers();
+
         let path = path.as_ref();
+        self.last_now = now;
 
         match self.dirs.iter_mut().find(|dir| dir.path == path) {
             None => {
@@ -59,10 +252,12 @@ impl<'file> Database<'file> {
             }
         };
 
-        self.modified = true;
+        self.log_mutation(|log| log.append_add(path, now, 1.0));
     }
 
     pub fn dedup(&mut self) {
+        self.unmerge_read_only_layers();
+
         // Sort by path, so that equal paths are next to each other.
         self.dirs.sort_by(|dir1, dir2| dir1.path.cmp(&dir2.path));
 
@@ -83,23 +278,113 @@ impl<'file> Database<'file> {
 
             // Delete curr_dir.
             self.dirs.swap_remove(idx);
+
+            // Dedup rewrites entries out-of-band from the log, just like
+            // `age`, so it must force a fresh snapshot rather than rely on
+            // the log ever catching up.
             self.modified = true;
+            self.force_compact = true;
         }
     }
 
-    // Streaming iterator for directories.
+    /// Streaming iterator for directories, merged across every layer.
+    ///
+    /// Read-only layers are folded into the writable layer's list the same
+    /// way [`dedup`](Self::dedup) merges exact duplicates within a single
+    /// layer: ranks are summed, and the most recent `last_accessed` wins.
+    /// The merge is undone again by `unmerge_read_only_layers` at the start
+    /// of every other `&mut self` method, so it never leaks into anything
+    /// persisted or mutated after `stream` returns -- callers can freely
+    /// mix `stream` with `add`/`remove`/`age`/`save` in any order.
     pub fn stream(&mut self, now: Epoch) -> Stream<'_, 'file> {
+        self.merge_read_only_layers();
         Stream::new(self, now)
     }
 
+    fn merge_read_only_layers(&mut self) {
+        if self.layers_merged {
+            return;
+        }
+
+        for layer in &self.read_only {
+            for dir in layer.iter() {
+                match self.dirs.iter_mut().find(|existing| existing.path == dir.path) {
+                    Some(existing) => {
+                        // Only the *first* read-only layer to touch this
+                        // path should record its pre-merge value -- if a
+                        // second layer also has this path, `existing` has
+                        // already been folded into by the first, and
+                        // overwriting the saved value here would mean
+                        // `unmerge` restores `orig + layer1` instead of
+                        // `orig`.
+                        let already_recorded = self
+                            .merged_new
+                            .iter()
+                            .any(|p| p.as_str() == dir.path.as_ref())
+                            || self
+                                .merged_overlay
+                                .iter()
+                                .any(|(p, ..)| p.as_str() == dir.path.as_ref());
+                        if !already_recorded {
+                            self.merged_overlay.push((
+                                existing.path.to_string(),
+                                existing.last_accessed,
+                                existing.rank,
+                            ));
+                        }
+                        existing.last_accessed = existing.last_accessed.max(dir.last_accessed);
+                        existing.rank += dir.rank;
+                    }
+                    None => {
+                        self.merged_new.push(dir.path.to_string());
+                        self.dirs.push(Dir {
+                            path: dir.path.to_string().into(),
+                            last_accessed: dir.last_accessed,
+                            rank: dir.rank,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.layers_merged = true;
+    }
+
+    /// Undoes `merge_read_only_layers`, restoring `dirs` to just the
+    /// writable layer's own entries. Any `Stream` borrowing this `Database`
+    /// from an earlier `stream` call is guaranteed to have already been
+    /// dropped by the time another `&mut self` method can run, so this is
+    /// safe to call unconditionally at the start of every one of them.
+    fn unmerge_read_only_layers(&mut self) {
+        if !self.layers_merged {
+            return;
+        }
+
+        for path in self.merged_new.drain(..) {
+            if let Some(idx) = self.dirs.iter().position(|dir| dir.path == path) {
+                self.dirs.swap_remove(idx);
+            }
+        }
+        for (path, last_accessed, rank) in self.merged_overlay.drain(..) {
+            if let Some(existing) = self.dirs.iter_mut().find(|dir| dir.path == path) {
+                existing.last_accessed = last_accessed;
+                existing.rank = rank;
+            }
+        }
+
+        self.layers_merged = false;
+    }
+
     /// Removes the directory with `path` from the store.
     /// This does not preserve ordering, but is O(1).
     pub fn remove<S: AsRef<str>>(&mut self, path: S) -> bool {
+        self.unmerge_read_only_layers();
+
         let path = path.as_ref();
 
         if let Some(idx) = self.dirs.iter().position(|dir| dir.path == path) {
             self.dirs.swap_remove(idx);
-            self.modified = true;
+            self.log_mutation(|log| log.append_remove(path));
             return true;
         }
 
@@ -107,6 +392,8 @@ impl<'file> Database<'file> {
     }
 
     pub fn age(&mut self, max_age: Rank) {
+        self.unmerge_read_only_layers();
+
         let sum_age = self.dirs.iter().map(|dir| dir.rank).sum::<Rank>();
 
         if sum_age > max_age {
@@ -120,7 +407,10 @@ impl<'file> Database<'file> {
                 }
             }
 
+            // Aging rescales every entry at once, so it's recorded as a
+            // fresh snapshot rather than a flood of per-entry log records.
             self.modified = true;
+            self.force_compact = true;
         }
     }
 }
@@ -173,41 +463,183 @@ fn persist<P: AsRef<Path>>(file: NamedTempFile, path: P) -> Result<(), PersistEr
 }
 
 pub struct DatabaseFile {
-    buffer: Vec<u8>,
-    data_dir: PathBuf,
+    layers: Vec<Layer>,
+    buffers: Vec<Vec<u8>>,
+    compression_level: i32,
+    backup_depth: usize,
+    max_entries: Option<usize>,
+    max_bytes: Option<u64>,
 }
 
 impl DatabaseFile {
     pub fn new<P: Into<PathBuf>>(data_dir: P) -> Self {
-        DatabaseFile { buffer: Vec::new(), data_dir: data_dir.into() }
+        DatabaseFile::with_layers(vec![Layer::writable(data_dir)])
+    }
+
+    /// Opens a database backed by an ordered list of layers, e.g. a
+    /// curated, read-only system-wide database with the user's own
+    /// writable one mounted on top. Exactly one layer must be writable;
+    /// the rest are merged in for ranking only, and are never modified.
+    pub fn with_layers(layers: Vec<Layer>) -> Self {
+        let buffers = vec![Vec::new(); layers.len()];
+        DatabaseFile {
+            layers,
+            buffers,
+            compression_level: compress::DEFAULT_LEVEL,
+            backup_depth: backup::DEFAULT_DEPTH,
+            max_entries: None,
+            max_bytes: None,
+        }
+    }
+
+    /// Sets the zstd level used when compacting the writable layer's
+    /// snapshot. Defaults to a fast level, trading compression ratio for
+    /// CPU time.
+    pub fn with_compression_level(mut self, level: i32) -> Self {
+        self.compression_level = level;
+        self
+    }
+
+    /// Sets how many rotated `db.zo.bak.*` backups to keep for the
+    /// writable layer.
+    pub fn with_backup_depth(mut self, depth: usize) -> Self {
+        self.backup_depth = depth;
+        self
+    }
+
+    /// Caps the writable layer at `max_entries` directories, evicting the
+    /// lowest-scoring ones on the next compaction once exceeded.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Caps the writable layer's on-disk snapshot at `max_bytes`, evicting
+    /// the lowest-scoring entries on the next compaction once exceeded.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
     }
 
     pub fn open(&mut self) -> Result<Database> {
-        // Read the entire database to memory. For smaller files, this is
-        // faster than mmap / streaming, and allows for zero-copy
-        // deserialization.
-        let path = db_path(&self.data_dir);
-        match fs::read(&path) {
-            Ok(buffer) => {
-                self.buffer = buffer;
-                let dirs = DirList::from_bytes(&self.buffer).with_context(|| {
-                    format!("could not deserialize database: {}", path.display())
-                })?;
-                Ok(Database { dirs, modified: false, data_dir: &self.data_dir })
-            }
-            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+        let writable_idx = self
+            .layers
+            .iter()
+            .position(|layer| layer.state.is_writable())
+            .context("no writable layer configured")?;
+
+        let mut snapshot_len = 0;
+        let mut snapshot_generation = 0;
+
+        // First pass: decode every layer's bytes into `self.buffers`. This
+        // has to fully finish before any `DirList::from_bytes` borrows a
+        // buffer -- those borrows live as long as the returned `Database`,
+        // so interleaving them with later `self.buffers[idx] = ..` writes
+        // would mean borrowing `self.buffers` mutably while it's still
+        // borrowed immutably.
+        for (idx, layer) in self.layers.iter().enumerate() {
+            self.buffers[idx] = if idx == writable_idx {
                 // Create data directory, but don't create any file yet.
-                // The file will be created later by [`Database::save`]
-                // if any data is modified.
-                fs::create_dir_all(&self.data_dir).with_context(|| {
-                    format!("unable to create data directory: {}", self.data_dir.display())
+                // The file will be created later by [`Database::save`] if
+                // any data is modified.
+                fs::create_dir_all(&layer.data_dir).with_context(|| {
+                    format!("unable to create data directory: {}", layer.data_dir.display())
                 })?;
-                Ok(Database { dirs: DirList::new(), modified: false, data_dir: &self.data_dir })
-            }
-            Err(e) => {
-                Err(e).with_context(|| format!("could not read from database: {}", path.display()))
+
+                match backup::read(&layer.data_dir, self.backup_depth)? {
+                    Some((raw_len, generation, decoded)) => {
+                        snapshot_len = raw_len;
+                        snapshot_generation = generation;
+                        decoded
+                    }
+                    None => Vec::new(),
+                }
+            } else {
+                let path = db_path(&layer.data_dir);
+                match fs::read(&path) {
+                    Ok(buffer) => match compress::decode(buffer) {
+                        Ok((_, decoded)) => decoded,
+                        Err(e) => {
+                            let _ = writeln!(
+                                io::stderr(),
+                                "zoxide: ignoring unreadable database: {}: {:?}",
+                                path.display(),
+                                e
+                            );
+                            Vec::new()
+                        }
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+                    Err(e) => {
+                        return Err(e).with_context(|| {
+                            format!("could not read from database: {}", path.display())
+                        });
+                    }
+                }
+            };
+        }
+
+        // Second pass: every buffer is populated now, so it's safe to
+        // zero-copy deserialize all of them at once.
+        let mut dirs = None;
+        let mut read_only = Vec::new();
+        for (idx, layer) in self.layers.iter().enumerate() {
+            let buffer = &self.buffers[idx];
+            let layer_dirs = if buffer.is_empty() {
+                DirList::new()
+            } else if idx == writable_idx {
+                DirList::from_bytes(buffer).with_context(|| {
+                    format!(
+                        "could not deserialize database: {}",
+                        db_path(&layer.data_dir).display()
+                    )
+                })?
+            } else {
+                let path = db_path(&layer.data_dir);
+                match DirList::from_bytes(buffer) {
+                    Ok(dirs) => dirs,
+                    Err(e) => {
+                        let _ = writeln!(
+                            io::stderr(),
+                            "zoxide: ignoring corrupt database: {}: {:?}",
+                            path.display(),
+                            e
+                        );
+                        DirList::new()
+                    }
+                }
+            };
+
+            if idx == writable_idx {
+                dirs = Some(layer_dirs);
+            } else {
+                read_only.push(layer_dirs);
             }
         }
+        let mut dirs = dirs.expect("writable_idx is a valid index into self.layers");
+
+        let data_dir = &self.layers[writable_idx].data_dir;
+        let log = log::ChangeLog::open(data_dir, &mut dirs, snapshot_generation)
+            .with_context(|| format!("could not open change log in: {}", data_dir.display()))?;
+
+        Ok(Database {
+            dirs,
+            modified: false,
+            data_dir,
+            log,
+            snapshot_len,
+            snapshot_generation,
+            force_compact: false,
+            read_only,
+            layers_merged: false,
+            merged_new: Vec::new(),
+            merged_overlay: Vec::new(),
+            compression_level: self.compression_level,
+            backup_depth: self.backup_depth,
+            max_entries: self.max_entries,
+            max_bytes: self.max_bytes,
+            last_now: 0,
+        })
     }
 }
 
@@ -266,4 +698,115 @@ mod tests {
             assert!(!db.remove(path));
         }
     }
+
+    #[test]
+    fn layered() {
+        let now = 946684800;
+
+        let read_only_dir = tempfile::tempdir().unwrap();
+        {
+            let mut db = DatabaseFile::new(read_only_dir.path());
+            let mut db = db.open().unwrap();
+            db.add("/shared", now);
+        }
+
+        // Snapshot every file the read-only mount's setup left behind, so
+        // we can confirm below that mounting it read-only never touches
+        // any of them, rather than asserting on a file (`db.zo.log`) that
+        // this setup itself already created.
+        let read_only_files_before: Vec<(PathBuf, Vec<u8>)> = fs::read_dir(read_only_dir.path())
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let contents = fs::read(&path).unwrap();
+                (path, contents)
+            })
+            .collect();
+        assert!(!read_only_files_before.is_empty());
+
+        let writable_dir = tempfile::tempdir().unwrap();
+        {
+            let layers = vec![
+                Layer::read_only(read_only_dir.path()),
+                Layer::writable(writable_dir.path()),
+            ];
+            let mut db = DatabaseFile::with_layers(layers);
+            let mut db = db.open().unwrap();
+            db.add("/mine", now);
+
+            // Calling `stream` merges the read-only layer into `dirs`.
+            let _ = db.stream(now);
+            assert!(db.dirs.iter().any(|dir| dir.path == "/shared"));
+            assert!(db.dirs.iter().any(|dir| dir.path == "/mine"));
+        }
+
+        // The read-only layer itself must never be written to.
+        for (path, contents_before) in &read_only_files_before {
+            assert_eq!(&fs::read(path).unwrap(), contents_before);
+        }
+
+        // The merge must never leak into the writable layer's own
+        // snapshot: reopening without the read-only layer should see only
+        // what was actually added to the writable layer.
+        let mut db = DatabaseFile::new(writable_dir.path());
+        let db = db.open().unwrap();
+        assert_eq!(db.dirs.len(), 1);
+        assert_eq!(db.dirs[0].path, "/mine");
+    }
+
+    #[test]
+    fn legacy_uncompressed_database_still_loads() {
+        let now = 946684800;
+        let data_dir = tempfile::tempdir().unwrap();
+
+        // A pre-compression snapshot has no magic header.
+        let mut dirs = DirList::new();
+        dirs.push(Dir { path: "/foo/bar".to_string().into(), last_accessed: now, rank: 1.0 });
+        fs::write(data_dir.path().join("db.zo"), dirs.to_bytes().unwrap()).unwrap();
+
+        let mut db = DatabaseFile::new(data_dir.path());
+        let db = db.open().unwrap();
+        assert_eq!(db.dirs.len(), 1);
+        assert_eq!(db.dirs[0].path, "/foo/bar");
+    }
+
+    #[test]
+    fn recovers_from_corrupt_database() {
+        let now = 946684800;
+        let data_dir = tempfile::tempdir().unwrap();
+
+        {
+            let mut db = DatabaseFile::new(data_dir.path());
+            let mut db = db.open().unwrap();
+            db.add("/foo/bar", now);
+        }
+
+        // Corrupt the snapshot. With no backups to fall back to, `open`
+        // should still succeed, just with an empty database.
+        fs::write(data_dir.path().join("db.zo"), b"not a valid snapshot").unwrap();
+
+        let mut db = DatabaseFile::new(data_dir.path());
+        let db = db.open().unwrap();
+        assert!(db.dirs.is_empty());
+        assert!(data_dir.path().join("db.zo.corrupt").exists());
+    }
+
+    #[test]
+    fn enforces_max_entries() {
+        let now = 946684800;
+        let data_dir = tempfile::tempdir().unwrap();
+
+        {
+            let mut db = DatabaseFile::new(data_dir.path()).with_max_entries(1);
+            let mut db = db.open().unwrap();
+            db.add("/foo/bar", now); // rank 2.0, higher-scoring
+            db.add("/foo/bar", now);
+            db.add("/baz", now - 1); // rank 1.0, evicted to make room
+        }
+
+        let mut db = DatabaseFile::new(data_dir.path());
+        let db = db.open().unwrap();
+        assert_eq!(db.dirs.len(), 1);
+        assert_eq!(db.dirs[0].path, "/foo/bar");
+    }
 }